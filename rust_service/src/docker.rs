@@ -0,0 +1,91 @@
+// Minimal client for the Docker Engine HTTP API spoken over the daemon's Unix
+// socket (/var/run/docker.sock). We build a hyper client on a Unix-socket
+// connector and hit the container endpoints we need to health-check and
+// remediate the python_agent service that /compute depends on.
+use hyper::{Body, Client, Method, Request, StatusCode};
+use hyper::body;
+use hyperlocal::{UnixClientExt, UnixConnector, Uri as UnixUri};
+use serde_json::Value;
+
+pub struct DockerClient {
+    socket: String,
+    client: Client<UnixConnector, Body>,
+}
+
+impl DockerClient {
+    // Point the client at the daemon socket (default /var/run/docker.sock).
+    pub fn new(socket: impl Into<String>) -> Self {
+        DockerClient { socket: socket.into(), client: Client::unix() }
+    }
+
+    // Build a hyperlocal URI targeting `path` on the configured socket.
+    fn uri(&self, path: &str) -> hyper::Uri {
+        UnixUri::new(&self.socket, path).into()
+    }
+
+    // Drain a response body into a String.
+    async fn read_body(resp: hyper::Response<Body>) -> Result<String, String> {
+        let bytes = body::to_bytes(resp.into_body())
+            .await
+            .map_err(|e| format!("read docker body: {}", e))?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| format!("decode docker body: {}", e))
+    }
+
+    // GET /containers/{id}/json => full container inspect, parsed as JSON.
+    pub async fn inspect_container(&self, name: &str) -> Result<Value, String> {
+        let uri = self.uri(&format!("/containers/{}/json", name));
+        let resp = self
+            .client
+            .get(uri)
+            .await
+            .map_err(|e| format!("docker inspect request: {}", e))?;
+        if resp.status() != StatusCode::OK {
+            return Err(format!("docker inspect returned {}", resp.status()));
+        }
+        let text = Self::read_body(resp).await?;
+        serde_json::from_str(&text).map_err(|e| format!("parse inspect json: {}", e))
+    }
+
+    // POST /containers/{id}/restart => trigger a restart; 204 on success.
+    pub async fn restart_container(&self, name: &str) -> Result<(), String> {
+        let uri = self.uri(&format!("/containers/{}/restart", name));
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .body(Body::empty())
+            .map_err(|e| format!("build restart request: {}", e))?;
+        let resp = self
+            .client
+            .request(req)
+            .await
+            .map_err(|e| format!("docker restart request: {}", e))?;
+        match resp.status() {
+            StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
+            other => Err(format!("docker restart returned {}", other)),
+        }
+    }
+
+    // GET /containers/{id}/logs => recent stdout/stderr as a single string.
+    pub async fn container_logs(&self, name: &str) -> Result<String, String> {
+        let uri = self.uri(&format!("/containers/{}/logs?stdout=1&stderr=1&tail=100", name));
+        let resp = self
+            .client
+            .get(uri)
+            .await
+            .map_err(|e| format!("docker logs request: {}", e))?;
+        if resp.status() != StatusCode::OK {
+            return Err(format!("docker logs returned {}", resp.status()));
+        }
+        Self::read_body(resp).await
+    }
+
+    // Convenience: is the named container running right now?
+    pub async fn is_running(&self, name: &str) -> Result<bool, String> {
+        let info = self.inspect_container(name).await?;
+        Ok(info
+            .get("State")
+            .and_then(|s| s.get("Running"))
+            .and_then(|r| r.as_bool())
+            .unwrap_or(false))
+    }
+}
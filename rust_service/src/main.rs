@@ -1,8 +1,25 @@
-use hyper::{Body, Request, Response, Server, header::CONTENT_TYPE, StatusCode};
+use hyper::{Body, Method, Request, Response, Server, header::CONTENT_TYPE, StatusCode};
 use hyper::service::{make_service_fn, service_fn};
 use std::collections::HashMap;
 use std::convert::Infallible;
-use reqwest;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+mod docker;
+use docker::DockerClient;
+
+// Peer credentials of the connecting process, read from a Unix domain socket via
+// SO_PEERCRED and threaded into the request so handlers can authorize by caller.
+// Absent for TCP connections, where the kernel cannot supply them.
+#[derive(Clone, Copy, Debug)]
+struct PeerCred {
+    uid: u32,
+    gid: u32,
+    pid: Option<i32>,
+}
 
 // parse_query: splits "prompt=Hello&x=1" into a HashMap
 fn parse_query(query_str: &str) -> HashMap<String, String> {
@@ -18,69 +35,954 @@ fn parse_query(query_str: &str) -> HashMap<String, String> {
     params
 }
 
+// RouteCtx carries everything a handler might want: the original request plus
+// any dynamic segments captured from the path (e.g. :name in /agents/:name/compute).
+struct RouteCtx {
+    req: Request<Body>,
+    params: HashMap<String, String>,
+}
+
+// Handlers are boxed async fns returning a ready Response. We keep the
+// Infallible contract of the old handlers by resolving straight to a Response.
+type BoxFut = Pin<Box<dyn Future<Output = Response<Body>> + Send>>;
+type Handler = Box<dyn Fn(RouteCtx) -> BoxFut + Send + Sync>;
+
+// A single path segment in a registered pattern: either a literal or a :param.
+enum Seg {
+    Static(String),
+    Param(String),
+}
+
+struct Route {
+    method: Method,
+    segments: Vec<Seg>,
+    handler: Handler,
+}
+
+// Pre-request middleware observes the incoming request (e.g. logging); it
+// cannot modify or reject the request, so a failed check cannot stop dispatch.
+// Post-response middleware mutates the outgoing response (CORS headers, etc).
+// They run as an ordered chain around every dispatch, so cross-cutting concerns
+// live in one place instead of being sprinkled through the handlers.
+type PreMw = Box<dyn Fn(&Request<Body>) + Send + Sync>;
+type PostMw = Box<dyn Fn(&mut Response<Body>) + Send + Sync>;
+
+struct Router {
+    routes: Vec<Route>,
+    pre: Vec<PreMw>,
+    post: Vec<PostMw>,
+}
+
+impl Router {
+    fn new() -> Self {
+        Router { routes: Vec::new(), pre: Vec::new(), post: Vec::new() }
+    }
+
+    // Register a handler for a (method, pattern) pair. Patterns use "/a/:b/c"
+    // syntax where ":b" captures a dynamic segment into RouteCtx::params.
+    fn route<F, Fut>(&mut self, method: Method, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(RouteCtx) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response<Body>> + Send + 'static,
+    {
+        let segments = pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                if let Some(name) = s.strip_prefix(':') {
+                    Seg::Param(name.to_string())
+                } else {
+                    Seg::Static(s.to_string())
+                }
+            })
+            .collect();
+        let boxed: Handler = Box::new(move |ctx| Box::pin(handler(ctx)));
+        self.routes.push(Route { method, segments, handler: boxed });
+        self
+    }
+
+    fn pre(&mut self, mw: PreMw) -> &mut Self {
+        self.pre.push(mw);
+        self
+    }
+
+    fn post(&mut self, mw: PostMw) -> &mut Self {
+        self.post.push(mw);
+        self
+    }
+
+    // Try to match a request path's segments against a route, returning the
+    // captured params on success. Lengths must match and every static segment
+    // must be equal; param segments capture whatever is in that position.
+    fn try_match(route: &Route, path_segs: &[&str]) -> Option<HashMap<String, String>> {
+        if route.segments.len() != path_segs.len() {
+            return None;
+        }
+        let mut params = HashMap::new();
+        for (seg, got) in route.segments.iter().zip(path_segs.iter()) {
+            match seg {
+                Seg::Static(s) => {
+                    if s != got {
+                        return None;
+                    }
+                }
+                Seg::Param(name) => {
+                    params.insert(name.clone(), (*got).to_string());
+                }
+            }
+        }
+        Some(params)
+    }
+
+    // Dispatch a request: run pre-middleware, find the first route whose method
+    // and pattern match, invoke its handler, then run post-middleware. An
+    // unmatched path yields a real 404.
+    async fn dispatch(&self, req: Request<Body>) -> Response<Body> {
+        for mw in &self.pre {
+            mw(&req);
+        }
+
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        let path_segs: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        let mut response = None;
+        for route in &self.routes {
+            if route.method != method {
+                continue;
+            }
+            if let Some(params) = Router::try_match(route, &path_segs) {
+                let ctx = RouteCtx { req, params };
+                response = Some((route.handler)(ctx).await);
+                break;
+            }
+        }
+
+        let mut resp = response.unwrap_or_else(|| {
+            let mut r = Response::new(Body::from("Not Found\n"));
+            *r.status_mut() = StatusCode::NOT_FOUND;
+            r.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+            r
+        });
+
+        for mw in &self.post {
+            mw(&mut resp);
+        }
+        resp
+    }
+}
+
 // /health => basic check
-async fn health_handler() -> Result<Response<Body>, Infallible> {
-    let resp = Response::new(Body::from("OK"));
-    Ok(resp)
+async fn health_handler(_ctx: RouteCtx) -> Response<Body> {
+    Response::new(Body::from("OK"))
+}
+
+// Retry/timeout policy for calls to the synergy agent. Loaded from the
+// environment in main so it can be tuned per deployment without a rebuild.
+#[derive(Clone)]
+struct UpstreamConfig {
+    base_delay: Duration,
+    max_retries: u32,
+    per_try_timeout: Duration,
+    deadline: Duration,
+}
+
+impl UpstreamConfig {
+    // Read UPSTREAM_BASE_DELAY_MS / UPSTREAM_MAX_RETRIES / UPSTREAM_TIMEOUT_MS /
+    // UPSTREAM_DEADLINE_MS, falling back to sensible defaults.
+    fn from_env() -> Self {
+        fn ms(key: &str, default: u64) -> Duration {
+            Duration::from_millis(
+                std::env::var(key).ok().and_then(|s| s.parse().ok()).unwrap_or(default),
+            )
+        }
+        UpstreamConfig {
+            base_delay: ms("UPSTREAM_BASE_DELAY_MS", 100),
+            max_retries: std::env::var("UPSTREAM_MAX_RETRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(3),
+            per_try_timeout: ms("UPSTREAM_TIMEOUT_MS", 5_000),
+            deadline: ms("UPSTREAM_DEADLINE_MS", 20_000),
+        }
+    }
+}
+
+// An error surfaced by call_upstream once retries are exhausted or the outcome
+// is non-retriable. `status` is what we hand back to the client.
+struct UpstreamError {
+    status: StatusCode,
+    message: String,
+}
+
+// How a single attempt landed. Retriable outcomes (transport errors, timeouts,
+// 429, 5xx) trigger a backoff; non-retriable 4xx propagate immediately.
+enum Attempt {
+    Success(String),
+    Retriable(String),
+    NonRetriable(StatusCode, String),
+}
+
+// Cheap jitter in [0, upper_ms) without pulling in a rng crate: mix the current
+// nanos so concurrent retriers don't all wake on the same tick (thundering herd).
+fn jitter_ms(upper_ms: u64) -> u64 {
+    if upper_ms == 0 {
+        return 0;
+    }
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    nanos % upper_ms
 }
 
-// /compute => parse 'prompt' & call synergy agent
-async fn compute_handler(query: &str) -> Result<Response<Body>, Infallible> {
+// Whether a non-success upstream status should be retried: 429 and any 5xx are
+// transient, other 4xx are client errors retrying won't fix. Split out as a pure
+// fn so the classification can be unit-tested without a live upstream.
+fn status_is_retriable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Run a single attempt with a per-try timeout and classify the result. The
+// caller supplies a fresh RequestBuilder each time since send() consumes it.
+async fn attempt_once(builder: reqwest::RequestBuilder) -> Attempt {
+    match builder.send().await {
+        Ok(resp) => {
+            let status = resp.status();
+            if status.is_success() {
+                match resp.text().await {
+                    Ok(text) => Attempt::Success(text),
+                    Err(e) => Attempt::Retriable(format!("read body: {}", e)),
+                }
+            } else if status_is_retriable(status) {
+                Attempt::Retriable(format!("upstream status {}", status))
+            } else {
+                // 4xx (other than 429): a client error that retrying won't fix.
+                let body = resp.text().await.unwrap_or_default();
+                Attempt::NonRetriable(status, body)
+            }
+        }
+        // Connect errors, timeouts and other transport failures are retriable.
+        Err(e) => Attempt::Retriable(format!("transport error: {}", e)),
+    }
+}
+
+// Reusable resilient upstream call: retries retriable outcomes with exponential
+// backoff + jitter, bounded by max_retries and an overall deadline. Returns the
+// response body on success or an UpstreamError carrying the status to surface.
+// `build` produces a fresh RequestBuilder per attempt so method/body/headers
+// stay independent of the GET vs POST shape of the upstream.
+async fn call_upstream<F>(
+    build: F,
+    cfg: &UpstreamConfig,
+) -> Result<String, UpstreamError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let start = std::time::Instant::now();
+    let mut last_err = String::from("no attempt made");
+
+    for attempt in 0..=cfg.max_retries {
+        match attempt_once(build()).await {
+            Attempt::Success(text) => return Ok(text),
+            Attempt::NonRetriable(status, body) => {
+                return Err(UpstreamError { status, message: body });
+            }
+            Attempt::Retriable(msg) => {
+                last_err = msg;
+                if attempt == cfg.max_retries {
+                    break;
+                }
+                // base * 2^attempt, plus jitter up to one base delay. Cap the
+                // shift and use saturating arithmetic so a misconfigured
+                // UPSTREAM_MAX_RETRIES / base delay can't overflow and panic; the
+                // overall deadline below is the real bound on how long we wait.
+                let factor = 1u64.checked_shl(attempt.min(32)).unwrap_or(u64::MAX);
+                let backoff = cfg
+                    .base_delay
+                    .saturating_mul(factor.min(u32::MAX as u64) as u32)
+                    .saturating_add(Duration::from_millis(jitter_ms(cfg.base_delay.as_millis() as u64)));
+                if start.elapsed() + backoff >= cfg.deadline {
+                    break;
+                }
+                tokio::time::sleep(backoff).await;
+            }
+        }
+    }
+
+    Err(UpstreamError {
+        status: StatusCode::BAD_GATEWAY,
+        message: format!("synergy agent unavailable after retries: {}", last_err),
+    })
+}
+
+// How a backend expects the prompt to be delivered. The original python_agent
+// takes the prompt in the query string; OpenAI-compatible servers want a JSON
+// chat-completion body POSTed to /v1/chat/completions.
+enum BackendKind {
+    PromptQuery,
+    OpenAiChat,
+}
+
+// A single addressable LLM backend. `base_url` + `endpoint` form the target;
+// `model` and `api_key` populate the chat-completion body/Authorization header
+// for OpenAI-compatible backends.
+struct Backend {
+    base_url: String,
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+    kind: BackendKind,
+}
+
+// Registry of backends addressable by the /compute `model` query param. Loaded
+// once at startup so unknown models can be rejected with the available names.
+struct BackendRegistry {
+    backends: HashMap<String, Backend>,
+}
+
+impl BackendRegistry {
+    // Always register the local python_agent synergy backend. Additionally
+    // register an "openai" backend when OPENAI_API_KEY is set, honouring
+    // OPENAI_BASE_URL / OPENAI_MODEL overrides for any compatible server.
+    fn from_env() -> Self {
+        let mut backends = HashMap::new();
+        backends.insert(
+            "python_agent".to_string(),
+            Backend {
+                base_url: "http://python_agent:9000".to_string(),
+                endpoint: "/chat_gpt".to_string(),
+                model: "synergy".to_string(),
+                api_key: None,
+                kind: BackendKind::PromptQuery,
+            },
+        );
+        if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+            backends.insert(
+                "openai".to_string(),
+                Backend {
+                    base_url: std::env::var("OPENAI_BASE_URL")
+                        .unwrap_or_else(|_| "https://api.openai.com".to_string()),
+                    endpoint: "/v1/chat/completions".to_string(),
+                    model: std::env::var("OPENAI_MODEL")
+                        .unwrap_or_else(|_| "gpt-3.5-turbo".to_string()),
+                    api_key: Some(key),
+                    kind: BackendKind::OpenAiChat,
+                },
+            );
+        }
+        BackendRegistry { backends }
+    }
+
+    fn names(&self) -> String {
+        let mut names: Vec<&str> = self.backends.keys().map(|s| s.as_str()).collect();
+        names.sort();
+        names.join(", ")
+    }
+}
+
+// Escape a string for embedding in a JSON string literal. We hand-build the
+// chat-completion body (as we already hand-build the python_agent URL) to keep
+// the dependency set unchanged.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// /compute => route the prompt to the backend named by the `model` query param
+// (defaulting to python_agent) and call it through the resilient upstream client.
+async fn compute_handler(
+    ctx: RouteCtx,
+    cfg: Arc<UpstreamConfig>,
+    registry: Arc<BackendRegistry>,
+) -> Response<Body> {
+    // When served over a Unix socket we know the caller's uid; optionally gate
+    // /compute to a single uid via COMPUTE_ALLOWED_UID for sidecar deployments.
+    if let Some(peer) = ctx.req.extensions().get::<PeerCred>() {
+        if let Ok(allowed) = std::env::var("COMPUTE_ALLOWED_UID") {
+            if allowed.parse::<u32>().ok() != Some(peer.uid) {
+                return error_response(
+                    StatusCode::FORBIDDEN,
+                    &format!("uid {} is not permitted to call /compute", peer.uid),
+                );
+            }
+        }
+    }
+
+    let query = ctx.req.uri().query().unwrap_or("");
     let params = parse_query(query);
     let prompt = params.get("prompt").map(|s| s.as_str()).unwrap_or("none");
+    // A dynamic path segment (/agents/:name/compute) names the backend directly;
+    // otherwise fall back to the `model` query param, then to python_agent.
+    let model = ctx
+        .params
+        .get("name")
+        .map(|s| s.as_str())
+        .or_else(|| params.get("model").map(|s| s.as_str()))
+        .unwrap_or("python_agent");
+
+    let backend = match registry.backends.get(model) {
+        Some(b) => b,
+        None => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("unknown model '{}'; available backends: {}", model, registry.names()),
+            );
+        }
+    };
+
+    // Streaming mode: ?stream=1 or `Accept: text/event-stream`. Pipe the upstream
+    // body through as SSE `data:` events so LLM output arrives token-by-token.
+    let wants_stream = params.get("stream").map(|v| v == "1" || v == "true").unwrap_or(false)
+        || ctx
+            .req
+            .headers()
+            .get(hyper::header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.contains("text/event-stream"))
+            .unwrap_or(false);
+
+    // The non-streaming path uses reqwest's total `.timeout()` as the per-try
+    // deadline. That would be wrong for streaming: `.timeout()` covers the whole
+    // request body too, so it would abort any SSE stream outlasting the per-try
+    // window. The streaming client therefore bounds only connection setup and
+    // lets the body run for as long as the upstream keeps emitting tokens.
+    let client = if wants_stream {
+        reqwest::Client::builder().connect_timeout(cfg.per_try_timeout).build()
+    } else {
+        reqwest::Client::builder().timeout(cfg.per_try_timeout).build()
+    };
+    let client = match client {
+        Ok(c) => c,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("client build: {}", e)),
+    };
+
+    // Build a fresh RequestBuilder per attempt, shaped to the backend's kind.
+    let build = || match backend.kind {
+        BackendKind::PromptQuery => {
+            let url = format!("{}{}?prompt={}", backend.base_url, backend.endpoint, prompt);
+            client.get(url)
+        }
+        BackendKind::OpenAiChat => {
+            let url = format!("{}{}", backend.base_url, backend.endpoint);
+            let body = format!(
+                "{{\"model\":\"{}\",\"messages\":[{{\"role\":\"user\",\"content\":\"{}\"}}]}}",
+                json_escape(&backend.model),
+                json_escape(prompt),
+            );
+            let mut rb = client
+                .post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body);
+            if let Some(key) = &backend.api_key {
+                rb = rb.header("Authorization", format!("Bearer {}", key));
+            }
+            rb
+        }
+    };
+
+    if wants_stream {
+        return stream_upstream(build()).await;
+    }
+
+    match call_upstream(build, &cfg).await {
+        Ok(synergy_resp) => {
+            let answer = format!("Rust synergy result => Python synergy says:\n{}\n", synergy_resp);
+            let mut http_resp = Response::new(Body::from(answer));
+            http_resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+            http_resp
+        }
+        // Propagate the real failure status (BAD_GATEWAY for 5xx/outages, or the
+        // upstream's own 4xx) instead of hiding it inside a 200.
+        Err(err) => error_response(err.status, &err.message),
+    }
+}
 
-    // Call Python synergy agent at http://python_agent:9000/chat_gpt?prompt=...
-    // In Docker Compose, "python_agent" is the service name, port 9000 is mapped internally.
-    // We'll do a GET request with prompt param:
-    let synergy_url = format!("http://python_agent:9000/chat_gpt?prompt={}", prompt);
-
-    // Make the request. If it fails, we return an error string.
-    let synergy_resp = match reqwest::get(&synergy_url).await {
-        Ok(resp) => match resp.text().await {
-            Ok(text) => text,
-            Err(e) => format!("Error reading synergy text: {}", e)
-        },
-        Err(e) => format!("Error calling synergy agent: {}", e)
+// /admin/agent/status => report whether the python_agent container is running,
+// using the Docker daemon socket so failures can be diagnosed without shelling in.
+async fn agent_status_handler(docker: Arc<DockerClient>, container: String) -> Response<Body> {
+    match docker.is_running(&container).await {
+        Ok(running) => {
+            let mut resp = Response::new(Body::from(format!(
+                "{{\"container\":\"{}\",\"running\":{}}}\n",
+                json_escape(&container),
+                running,
+            )));
+            resp.headers_mut().insert(CONTENT_TYPE, "application/json".parse().unwrap());
+            resp
+        }
+        Err(e) => error_response(StatusCode::BAD_GATEWAY, &format!("docker status: {}", e)),
+    }
+}
+
+// /admin/agent/restart => restart the python_agent container to remediate a
+// wedged upstream without touching the host.
+async fn agent_restart_handler(docker: Arc<DockerClient>, container: String) -> Response<Body> {
+    match docker.restart_container(&container).await {
+        Ok(()) => {
+            let mut resp = Response::new(Body::from(format!("restarted {}\n", container)));
+            resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+            resp
+        }
+        Err(e) => error_response(StatusCode::BAD_GATEWAY, &format!("docker restart: {}", e)),
+    }
+}
+
+// /admin/agent/logs => return the python_agent container's recent stdout/stderr
+// so a wedged upstream can be diagnosed without shelling into the host.
+async fn agent_logs_handler(docker: Arc<DockerClient>, container: String) -> Response<Body> {
+    match docker.container_logs(&container).await {
+        Ok(logs) => {
+            let mut resp = Response::new(Body::from(logs));
+            resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+            resp
+        }
+        Err(e) => error_response(StatusCode::BAD_GATEWAY, &format!("docker logs: {}", e)),
+    }
+}
+
+// Open the upstream request and stream its body to the client as Server-Sent
+// Events. Each upstream chunk becomes a `data:` event and a keep-alive comment
+// is emitted periodically so idle proxies don't drop the connection. Dropping
+// the returned Body (e.g. on client disconnect) drops the upstream response,
+// which aborts the in-flight request rather than leaking it.
+async fn stream_upstream(builder: reqwest::RequestBuilder) -> Response<Body> {
+    use futures_util::StreamExt;
+
+    let resp = match builder.send().await {
+        Ok(r) => r,
+        Err(e) => return error_response(StatusCode::BAD_GATEWAY, &format!("synergy agent unavailable: {}", e)),
+    };
+    if resp.status().is_server_error() {
+        return error_response(StatusCode::BAD_GATEWAY, &format!("upstream status {}", resp.status()));
+    }
+
+    let sse = async_stream::stream! {
+        let mut bytes = resp.bytes_stream();
+        let mut keep_alive = tokio::time::interval(Duration::from_secs(15));
+        keep_alive.tick().await; // consume the immediate first tick
+        loop {
+            tokio::select! {
+                chunk = bytes.next() => match chunk {
+                    Some(Ok(b)) => {
+                        let event = format!("data: {}\n\n", String::from_utf8_lossy(&b));
+                        yield Ok::<_, std::io::Error>(hyper::body::Bytes::from(event));
+                    }
+                    Some(Err(e)) => {
+                        yield Ok(hyper::body::Bytes::from(format!("event: error\ndata: {}\n\n", e)));
+                        break;
+                    }
+                    None => break,
+                },
+                _ = keep_alive.tick() => {
+                    yield Ok(hyper::body::Bytes::from(": keep-alive\n\n"));
+                }
+            }
+        }
     };
 
-    let answer = format!("Rust synergy result => Python synergy says:\n{}\n", synergy_resp);
+    let mut http_resp = Response::new(Body::wrap_stream(sse));
+    http_resp.headers_mut().insert(CONTENT_TYPE, "text/event-stream".parse().unwrap());
+    http_resp.headers_mut().insert(hyper::header::CACHE_CONTROL, "no-cache".parse().unwrap());
+    http_resp
+}
 
-    let mut http_resp = Response::new(Body::from(answer));
-    http_resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
-    Ok(http_resp)
+// Small helper for plain-text error responses with an explicit status code.
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let mut resp = Response::new(Body::from(format!("{}\n", message)));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+    resp
 }
 
 // default => hello from Rust
-async fn root_handler() -> Result<Response<Body>, Infallible> {
+async fn root_handler(_ctx: RouteCtx) -> Response<Body> {
     let msg = "Hello from Rust. Try /health or /compute?prompt=Hello\n";
     let mut resp = Response::new(Body::from(msg));
     resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
-    Ok(resp)
+    resp
 }
 
-async fn handle_req(req: Request<Body>) -> Result<Response<Body>, Infallible> {
-    let path = req.uri().path();
-    let query_str = req.uri().query().unwrap_or("");
+// /admin/shutdown => trip the shutdown coordinator so orchestration (or an
+// operator) can drain the server without sending a signal.
+async fn shutdown_handler(shutdown: Arc<Notify>) -> Response<Body> {
+    shutdown.notify_one();
+    let mut resp = Response::new(Body::from("draining\n"));
+    resp.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+    resp
+}
 
-    match path {
-        "/health" => health_handler().await,
-        "/compute" => compute_handler(query_str).await,
-        _ => root_handler().await,
+// Build the router once and register every handler against it. This replaces
+// the old hard-coded `match path` in handle_req: new endpoints are added here
+// and cross-cutting concerns go through the middleware chain.
+fn build_router(
+    shutdown: Arc<Notify>,
+    upstream: Arc<UpstreamConfig>,
+    registry: Arc<BackendRegistry>,
+    docker: Arc<DockerClient>,
+    agent_container: String,
+) -> Router {
+    let mut router = Router::new();
+    router
+        .pre(Box::new(|req| {
+            println!("--> {} {}", req.method(), req.uri().path());
+        }))
+        .post(Box::new(|resp| {
+            // Basic CORS so browser clients can hit /compute directly.
+            resp.headers_mut().insert("Access-Control-Allow-Origin", "*".parse().unwrap());
+        }));
+    {
+        // Address a specific backend by name through a dynamic path segment,
+        // e.g. GET /agents/openai/compute?prompt=Hi.
+        let upstream = upstream.clone();
+        let registry = registry.clone();
+        router.route(Method::GET, "/agents/:name/compute", move |ctx| {
+            let upstream = upstream.clone();
+            let registry = registry.clone();
+            async move { compute_handler(ctx, upstream, registry).await }
+        });
+    }
+    router
+        .route(Method::GET, "/health", health_handler)
+        .route(Method::GET, "/compute", move |ctx| {
+            let upstream = upstream.clone();
+            let registry = registry.clone();
+            async move { compute_handler(ctx, upstream, registry).await }
+        })
+        .route(Method::POST, "/admin/shutdown", move |_ctx| {
+            let shutdown = shutdown.clone();
+            async move { shutdown_handler(shutdown).await }
+        });
+    {
+        let docker = docker.clone();
+        let container = agent_container.clone();
+        router.route(Method::GET, "/admin/agent/status", move |_ctx| {
+            let docker = docker.clone();
+            let container = container.clone();
+            async move { agent_status_handler(docker, container).await }
+        });
+    }
+    {
+        let docker = docker.clone();
+        let container = agent_container.clone();
+        router.route(Method::GET, "/admin/agent/logs", move |_ctx| {
+            let docker = docker.clone();
+            let container = container.clone();
+            async move { agent_logs_handler(docker, container).await }
+        });
+    }
+    {
+        router.route(Method::POST, "/admin/agent/restart", move |_ctx| {
+            let docker = docker.clone();
+            let container = agent_container.clone();
+            async move { agent_restart_handler(docker, container).await }
+        });
     }
+    router.route(Method::GET, "/", root_handler);
+    router
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+// shutdown_signal resolves when any shutdown trigger fires: a SIGTERM (Docker
+// Compose's stop signal), a SIGINT (Ctrl-C), or the internal /admin/shutdown
+// route tripping `notify`. hyper's with_graceful_shutdown uses this future to
+// stop accepting new connections while letting in-flight requests finish.
+async fn shutdown_signal(notify: Arc<Notify>) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => println!("received SIGINT, draining..."),
+        _ = terminate => println!("received SIGTERM, draining..."),
+        _ = notify.notified() => println!("received /admin/shutdown, draining..."),
+    }
+}
+
+// Where the server should listen. Driven by LISTEN_MODE / LISTEN_UNIX_PATH env
+// vars, overridable with a `--unix <path>` CLI flag.
+#[derive(Debug, PartialEq)]
+enum ListenMode {
+    Tcp,
+    Unix(String),
+}
+
+impl ListenMode {
+    fn from_args_and_env() -> ListenMode {
+        Self::from_parts(
+            std::env::args().skip(1).collect(),
+            std::env::var("LISTEN_MODE").ok(),
+            std::env::var("LISTEN_UNIX_PATH").ok(),
+        )
+    }
+
+    // Pure selection logic, split out from the environment lookups so it can be
+    // unit-tested. The `--unix <path>` CLI flag wins over the env vars.
+    fn from_parts(args: Vec<String>, listen_mode: Option<String>, unix_path: Option<String>) -> ListenMode {
+        let mut it = args.into_iter();
+        while let Some(arg) = it.next() {
+            if arg == "--unix" {
+                if let Some(path) = it.next() {
+                    return ListenMode::Unix(path);
+                }
+            }
+        }
+        match listen_mode.as_deref() {
+            Some("unix") => ListenMode::Unix(
+                unix_path.unwrap_or_else(|| "/tmp/rust_service.sock".to_string()),
+            ),
+            _ => ListenMode::Tcp,
+        }
+    }
+}
+
+// Serve the handler stack over TCP with graceful shutdown (the original mode).
+async fn run_tcp(router: Arc<Router>, shutdown: Arc<Notify>, grace_secs: u64) -> Result<(), Box<dyn std::error::Error>> {
     let addr = ([0, 0, 0, 0], 3000).into();
     println!("Rust Service => listening on 0.0.0.0:3000");
-    println!("Try /health or /compute?prompt=Hello => calls Python synergy agent!");
 
-    let make_svc = make_service_fn(|_conn| async {
-        Ok::<_, Infallible>(service_fn(|req| async move {
-            handle_req(req).await
-        }))
+    let make_svc = make_service_fn(move |_conn| {
+        let router = router.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let router = router.clone();
+                async move { Ok::<_, Infallible>(router.dispatch(req).await) }
+            }))
+        }
     });
 
-    Server::bind(&addr).serve(make_svc).await?;
+    // Fires once the shutdown signal resolves, so the grace period below starts
+    // counting when draining begins rather than when the server starts.
+    let (drain_tx, drain_rx) = tokio::sync::oneshot::channel::<()>();
+    let server = Server::bind(&addr)
+        .serve(make_svc)
+        .with_graceful_shutdown(async move {
+            shutdown_signal(shutdown).await;
+            let _ = drain_tx.send(());
+        });
+    tokio::pin!(server);
+
+    // Bound the drain: if outstanding requests outlast the grace period we give
+    // up and exit so a wedged upstream can't block the shutdown indefinitely.
+    tokio::select! {
+        result = &mut server => result?,
+        _ = async {
+            let _ = drain_rx.await;
+            tokio::time::sleep(Duration::from_secs(grace_secs)).await;
+        } => {
+            eprintln!("grace period of {}s elapsed with requests still in flight, exiting", grace_secs);
+        }
+    }
+    Ok(())
+}
+
+// Serve the same handler stack over a Unix domain socket. We drive the accept
+// loop by hand so we can read each connection's SO_PEERCRED and stash it in the
+// request extensions before dispatch. New connections stop being accepted once
+// the shutdown signal fires; in-flight ones are bounded by the grace period.
+#[cfg(unix)]
+async fn run_unix(path: String, router: Arc<Router>, shutdown: Arc<Notify>, grace_secs: u64) -> Result<(), Box<dyn std::error::Error>> {
+    use hyper::server::conn::Http;
+    use tokio::net::UnixListener;
+
+    // Remove a stale socket file left by a previous crash/unclean exit.
+    if std::path::Path::new(&path).exists() {
+        let _ = std::fs::remove_file(&path);
+    }
+    let listener = UnixListener::bind(&path)?;
+    println!("Rust Service => listening on unix:{}", path);
+
+    let signal = shutdown_signal(shutdown.clone());
+    tokio::pin!(signal);
+
+    // Handles of the per-connection tasks, so we can await in-flight requests on
+    // shutdown instead of dropping them. Completed handles are reaped opportunistically
+    // to keep the set from growing without bound over the server's lifetime.
+    let mut conns: Vec<tokio::task::JoinHandle<()>> = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = &mut signal => break,
+            accepted = listener.accept() => {
+                let (stream, _addr) = match accepted {
+                    Ok(pair) => pair,
+                    Err(e) => { eprintln!("unix accept error: {}", e); continue; }
+                };
+                // SO_PEERCRED of the connecting process, if the platform exposes it.
+                let peer = stream.peer_cred().ok().map(|c| PeerCred {
+                    uid: c.uid(),
+                    gid: c.gid(),
+                    pid: c.pid(),
+                });
+                if let Some(p) = peer {
+                    println!("unix peer connected => uid={} gid={} pid={:?}", p.uid, p.gid, p.pid);
+                }
+                let router = router.clone();
+                conns.retain(|h| !h.is_finished());
+                conns.push(tokio::spawn(async move {
+                    let svc = service_fn(move |mut req: Request<Body>| {
+                        let router = router.clone();
+                        if let Some(peer) = peer {
+                            req.extensions_mut().insert(peer);
+                        }
+                        async move { Ok::<_, Infallible>(router.dispatch(req).await) }
+                    });
+                    if let Err(e) = Http::new().serve_connection(stream, svc).await {
+                        eprintln!("unix connection error: {}", e);
+                    }
+                }));
+            }
+        }
+    }
+
+    // Stop accepting (the loop has exited) and best-effort remove the socket file.
+    let _ = std::fs::remove_file(&path);
+    // Wait up to the full grace period for outstanding connections to wind down;
+    // if any outlast it we give up and exit so a wedged upstream can't block
+    // shutdown indefinitely — matching the TCP path's bounded drain.
+    let drain = futures_util::future::join_all(conns);
+    if tokio::time::timeout(Duration::from_secs(grace_secs), drain).await.is_err() {
+        eprintln!("grace period of {}s elapsed with connections still in flight, exiting", grace_secs);
+    }
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("Try /health or /compute?prompt=Hello => calls Python synergy agent!");
+
+    // Grace period the server waits for in-flight requests before hard-exiting.
+    let grace_secs: u64 = std::env::var("GRACEFUL_SHUTDOWN_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+
+    // Retry/timeout policy for upstream synergy calls, tunable via env.
+    let upstream = Arc::new(UpstreamConfig::from_env());
+
+    // Addressable LLM backends, loaded from the environment at startup.
+    let registry = Arc::new(BackendRegistry::from_env());
+    println!("Backends available => {}", registry.names());
+
+    // Docker control over the daemon socket, used by the /admin/agent routes.
+    let docker_socket = std::env::var("DOCKER_SOCKET")
+        .unwrap_or_else(|_| "/var/run/docker.sock".to_string());
+    let agent_container = std::env::var("AGENT_CONTAINER")
+        .unwrap_or_else(|_| "python_agent".to_string());
+    let docker = Arc::new(DockerClient::new(docker_socket));
+
+    // Shared handle used both by the signal watcher and the /admin/shutdown route.
+    let shutdown = Arc::new(Notify::new());
+    let router = Arc::new(build_router(
+        shutdown.clone(),
+        upstream.clone(),
+        registry.clone(),
+        docker.clone(),
+        agent_container,
+    ));
+
+    match ListenMode::from_args_and_env() {
+        ListenMode::Tcp => run_tcp(router, shutdown, grace_secs).await,
+        #[cfg(unix)]
+        ListenMode::Unix(path) => run_unix(path, router, shutdown, grace_secs).await,
+        #[cfg(not(unix))]
+        ListenMode::Unix(_) => {
+            eprintln!("unix socket mode is only supported on unix platforms");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Build a Route by registering a throwaway handler, so we can exercise the
+    // matcher without hand-constructing the boxed handler.
+    fn route_of(pattern: &str) -> Route {
+        let mut router = Router::new();
+        router.route(Method::GET, pattern, |_ctx| async { Response::new(Body::empty()) });
+        router.routes.pop().unwrap()
+    }
+
+    fn segs(path: &str) -> Vec<&str> {
+        path.split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    #[test]
+    fn try_match_captures_dynamic_segment() {
+        let route = route_of("/agents/:name/compute");
+        let params = Router::try_match(&route, &segs("/agents/openai/compute")).expect("should match");
+        assert_eq!(params.get("name").map(String::as_str), Some("openai"));
+    }
+
+    #[test]
+    fn try_match_rejects_length_and_literal_mismatch() {
+        let route = route_of("/agents/:name/compute");
+        assert!(Router::try_match(&route, &segs("/agents/openai")).is_none());
+        assert!(Router::try_match(&route, &segs("/agent/openai/compute")).is_none());
+    }
+
+    #[test]
+    fn status_classification_matches_retry_policy() {
+        assert!(status_is_retriable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(status_is_retriable(StatusCode::BAD_GATEWAY));
+        assert!(status_is_retriable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!status_is_retriable(StatusCode::BAD_REQUEST));
+        assert!(!status_is_retriable(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn json_escape_handles_quotes_and_control_chars() {
+        assert_eq!(json_escape("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+        assert_eq!(json_escape("\u{0001}"), "\\u0001");
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        assert_eq!(jitter_ms(0), 0);
+        assert!(jitter_ms(100) < 100);
+    }
+
+    #[test]
+    fn listen_mode_flag_beats_env() {
+        let m = ListenMode::from_parts(
+            vec!["--unix".into(), "/run/a.sock".into()],
+            Some("tcp".into()),
+            None,
+        );
+        assert_eq!(m, ListenMode::Unix("/run/a.sock".into()));
+    }
+
+    #[test]
+    fn listen_mode_env_unix_with_default_path() {
+        let m = ListenMode::from_parts(vec![], Some("unix".into()), None);
+        assert_eq!(m, ListenMode::Unix("/tmp/rust_service.sock".into()));
+    }
+
+    #[test]
+    fn listen_mode_defaults_to_tcp() {
+        assert_eq!(ListenMode::from_parts(vec![], None, None), ListenMode::Tcp);
+    }
+}